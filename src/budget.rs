@@ -0,0 +1,96 @@
+use solana_program::{
+    program_pack::Pack,
+    rent::Rent,
+};
+use spl_token::state::Account as TokenAccount;
+use crate::state::{Escrow, EscrowGroup, MAX_GROUP_MEMBERS};
+
+/// Size and rent-exempt balance of an account the escrow flow expects to be created
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AccountBudget {
+    pub size: usize,
+    pub lamports: u64,
+}
+
+impl AccountBudget {
+    pub fn new(rent: &Rent, size: usize) -> Self {
+        AccountBudget {
+            size,
+            lamports: rent.minimum_balance(size),
+        }
+    }
+}
+
+/// The escrow state account passed to InitEscrow or FlipAndRelist
+pub fn escrow_account_budget(rent: &Rent) -> AccountBudget {
+    AccountBudget::new(rent, Escrow::LEN)
+}
+
+/// The temp token account whose ownership is handed to the PDA
+pub fn temp_token_account_budget(rent: &Rent) -> AccountBudget {
+    AccountBudget::new(rent, TokenAccount::LEN)
+}
+
+/// The group account passed to GroupEscrows
+pub fn group_account_budget(rent: &Rent) -> AccountBudget {
+    AccountBudget::new(rent, EscrowGroup::LEN)
+}
+
+/// Every account created to list `escrow_count` escrows, plus their group account if `grouped`.
+/// None if a group of `escrow_count` escrows would be rejected by GroupEscrows.
+pub fn setup_budgets(rent: &Rent, escrow_count: usize, grouped: bool) -> Option<Vec<AccountBudget>> {
+    if grouped && !(2..=MAX_GROUP_MEMBERS).contains(&escrow_count) {
+        return None;
+    }
+
+    let capacity = escrow_count.checked_mul(2)?.checked_add(grouped as usize)?;
+    let mut budgets = Vec::with_capacity(capacity);
+
+    for _ in 0..escrow_count {
+        budgets.push(escrow_account_budget(rent));
+        budgets.push(temp_token_account_budget(rent));
+    }
+
+    if grouped {
+        budgets.push(group_account_budget(rent));
+    }
+
+    Some(budgets)
+}
+
+/// Total lamports needed to fund the given accounts, None on overflow
+pub fn total_lamports(budgets: &[AccountBudget]) -> Option<u64> {
+    budgets
+        .iter()
+        .try_fold(0u64, |total, budget| total.checked_add(budget.lamports))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_budgets() {
+        let rent = Rent::default();
+
+        let budgets = setup_budgets(&rent, 3, true).unwrap();
+        assert_eq!(budgets.len(), 7);
+        assert_eq!(budgets[0], escrow_account_budget(&rent));
+        assert_eq!(budgets[1], temp_token_account_budget(&rent));
+        assert_eq!(budgets[6].size, EscrowGroup::LEN);
+
+        assert_eq!(setup_budgets(&rent, 1, false).unwrap().len(), 2);
+        assert_eq!(setup_budgets(&rent, 1, true), None);
+        assert_eq!(setup_budgets(&rent, MAX_GROUP_MEMBERS + 1, true), None);
+    }
+
+    #[test]
+    fn test_total_lamports() {
+        let budget = AccountBudget { size: 0, lamports: 10 };
+        assert_eq!(total_lamports(&[budget, budget]), Some(20));
+        assert_eq!(total_lamports(&[]), Some(0));
+
+        let huge = AccountBudget { size: 0, lamports: u64::MAX };
+        assert_eq!(total_lamports(&[huge, budget]), None);
+    }
+}
@@ -11,6 +11,14 @@ pub enum EscrowError {
     EscrowAmountMismatch,
     #[error("Amount arithmetic overflow")]
     AmountOverflow,
+    #[error("Initializer's receive account is closed or no longer matches the escrow")]
+    StaleReceiveAccount,
+    #[error("Escrow does not belong to the expected group")]
+    EscrowGroupMismatch,
+    #[error("Invalid number of escrows in group")]
+    InvalidGroupSize,
+    #[error("Policy program is missing or does not match the escrow")]
+    PolicyProgramMismatch,
 }
 
 impl From<EscrowError> for ProgramError {
@@ -11,10 +11,15 @@ pub enum EscrowInstruction {
     /// 0. `[signer]` The account of the person initializing the escrow
     /// 1. `[writable]` Temporary token account that should be created prior to this instruction and owned by the initializer
     /// 2. `[]` The initializer's token account for the token they will receive should the trade go through
-    /// 3. `[writable]` The escrow account, it will hold all necessary info about the trade.
+    /// 3. `[writable]` The escrow account, it will hold all necessary info about the trade, sized to `Escrow::LEN`
     /// 4. `[]` The token program
+    /// 5. `[]` The policy program that has to approve the trade before it settles, only read if `with_policy` is set
+    ///
+    /// The receive account has to be an initialized token account, its owner and mint are recorded in the escrow.
     InitEscrow {
-        amount: u64
+        amount: u64,
+        /// optional trailing byte, clients that omit it create escrows without a policy program
+        with_policy: bool,
     },
     /// Accepts a trade
     ///
@@ -30,9 +35,67 @@ pub enum EscrowInstruction {
     /// 6. `[writable]` The escrow account holding the escrow info
     /// 7. `[]` The token program
     /// 8. `[]` The PDA account
+    /// 9. `[]` The escrow's policy program, only if the escrow references one
+    /// 10. `[]` Zero or more extra accounts the policy program needs to read, forwarded read-only and unsigned
     Exchange {
         /// the amount the taker expects to be paid in the other token, as a u64 because that's the max possible supply of a token
         amount: u64,
+    },
+    /// Relists the tokens received from a fill as a new escrow in the opposite direction
+    ///
+    /// `amount` has to be non-zero and the receive account (3) can't hold the same mint as the received tokens (1).
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The account of the person initializing the new escrow
+    /// 1. `[writable]` The initializer's token account that received tokens from the fill
+    /// 2. `[writable]` Temporary token account for the same mint, created prior to this instruction and owned by the initializer
+    /// 3. `[]` The initializer's token account for the token they will receive should the new trade go through
+    /// 4. `[writable]` The new escrow account, it will hold all necessary info about the trade.
+    /// 5. `[]` The token program
+    /// 6. `[]` The policy program that has to approve the new trade before it settles, only read if `with_policy` is set
+    FlipAndRelist {
+        /// the amount of received tokens to move into the new escrow
+        amount: u64,
+        /// the amount the initializer expects to be paid for the relisted tokens
+        expected_amount: u64,
+        /// optional trailing byte, the new escrow has no policy program when omitted
+        with_policy: bool,
+    },
+    /// Tags several escrows of the same initializer into a group that can only be filled all at once
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The account of the person who initialized the escrows
+    /// 1. `[writable]` The group account, it will hold the list of escrows in the group
+    /// 2. `[writable]` The escrow accounts to group, between 2 and `MAX_GROUP_MEMBERS` of them
+    GroupEscrows,
+    /// Accepts every trade in a group atomically
+    ///
+    ///
+    /// Accounts expected:
+    ///
+    /// 0. `[signer]` The account of the person taking the trades
+    /// 1. `[writable]` The group account, closed once every escrow is settled
+    /// 2. `[]` The token program
+    /// 3. `[]` The PDA account
+    ///
+    /// Then, for each escrow in the order it was grouped:
+    ///
+    /// 0. `[writable]` The taker's token account for the token they send
+    /// 1. `[writable]` The taker's token account for the token they will receive
+    /// 2. `[writable]` The PDA's temp token account to get tokens from and eventually close
+    /// 3. `[writable]` The initializer's main account to send their rent fees to
+    /// 4. `[writable]` The initializer's token account that will receive tokens
+    /// 5. `[writable]` The escrow account holding the escrow info
+    ///
+    /// Then the policy programs referenced by the grouped escrows, if any. Each policy program
+    /// receives every account listed after it, so its extra accounts go right after it.
+    ExchangeGroup {
+        /// the amounts the taker expects to be paid for each escrow, in group order
+        amounts: Vec<u64>,
     }
 }
 
@@ -43,10 +106,20 @@ impl EscrowInstruction {
         Ok(match tag {
             0 => Self::InitEscrow { 
                 amount: Self::unpack_amount(rest)?,
+                with_policy: Self::unpack_flag(rest.get(8..).ok_or(InvalidInstruction)?)?,
             },
             1 => Self::Exchange {
                 amount: Self::unpack_amount(rest)?
             },
+            2 => Self::FlipAndRelist {
+                amount: Self::unpack_amount(rest)?,
+                expected_amount: Self::unpack_amount(rest.get(8..).ok_or(InvalidInstruction)?)?,
+                with_policy: Self::unpack_flag(rest.get(16..).ok_or(InvalidInstruction)?)?,
+            },
+            3 => Self::GroupEscrows,
+            4 => Self::ExchangeGroup {
+                amounts: Self::unpack_amounts(rest)?,
+            },
             _ => return Err(InvalidInstruction.into()),
         })
     }
@@ -60,4 +133,102 @@ impl EscrowInstruction {
 
         Ok(amount)
     }
-}
\ No newline at end of file
+
+    fn unpack_flag(input: &[u8]) -> Result<bool, ProgramError> {
+        match input.first() {
+            None | Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => Err(InvalidInstruction.into()),
+        }
+    }
+
+    fn unpack_amounts(input: &[u8]) -> Result<Vec<u64>, ProgramError> {
+        let chunks = input.chunks_exact(8);
+        if input.is_empty() || !chunks.remainder().is_empty() {
+            return Err(InvalidInstruction.into());
+        }
+
+        chunks
+            .map(Self::unpack_amount)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unpack_flip_and_relist() {
+        let mut data = vec![2];
+        data.extend_from_slice(&10u64.to_le_bytes());
+        data.extend_from_slice(&20u64.to_le_bytes());
+
+        match EscrowInstruction::unpack(&data).unwrap() {
+            EscrowInstruction::FlipAndRelist { amount, expected_amount, with_policy } => {
+                assert_eq!(amount, 10);
+                assert_eq!(expected_amount, 20);
+                assert!(!with_policy);
+            },
+            _ => panic!("expected FlipAndRelist"),
+        }
+
+        data.push(1);
+        match EscrowInstruction::unpack(&data).unwrap() {
+            EscrowInstruction::FlipAndRelist { with_policy, .. } => assert!(with_policy),
+            _ => panic!("expected FlipAndRelist"),
+        }
+
+        assert_eq!(
+            EscrowInstruction::unpack(&data[..12]).err(),
+            Some(InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn test_unpack_init_escrow_policy_flag() {
+        let mut data = vec![0];
+        data.extend_from_slice(&5u64.to_le_bytes());
+
+        match EscrowInstruction::unpack(&data).unwrap() {
+            EscrowInstruction::InitEscrow { amount, with_policy } => {
+                assert_eq!(amount, 5);
+                assert!(!with_policy);
+            },
+            _ => panic!("expected InitEscrow"),
+        }
+
+        data.push(1);
+        match EscrowInstruction::unpack(&data).unwrap() {
+            EscrowInstruction::InitEscrow { with_policy, .. } => assert!(with_policy),
+            _ => panic!("expected InitEscrow"),
+        }
+
+        data[9] = 2;
+        assert_eq!(
+            EscrowInstruction::unpack(&data).err(),
+            Some(InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn test_unpack_exchange_group() {
+        let mut data = vec![4];
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.extend_from_slice(&2u64.to_le_bytes());
+
+        match EscrowInstruction::unpack(&data).unwrap() {
+            EscrowInstruction::ExchangeGroup { amounts } => assert_eq!(amounts, vec![1, 2]),
+            _ => panic!("expected ExchangeGroup"),
+        }
+
+        assert_eq!(
+            EscrowInstruction::unpack(&[4]).err(),
+            Some(InvalidInstruction.into())
+        );
+        assert_eq!(
+            EscrowInstruction::unpack(&data[..12]).err(),
+            Some(InvalidInstruction.into())
+        );
+    }
+}
@@ -2,6 +2,8 @@ pub mod instruction;
 pub mod error;
 pub mod processor;
 pub mod state;
+pub mod budget;
+pub mod policy;
 
 #[cfg(not(feature = "no-entrypoint"))]
 mod entrypoint;
\ No newline at end of file
@@ -0,0 +1,103 @@
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use crate::state::Escrow;
+
+/// Tag of the instruction the escrow program sends to a policy program before settling a fill
+pub const POLICY_CHECK_TAG: u8 = 0;
+
+/// Builds the standardized call a policy program receives before an escrow is settled.
+/// Any error returned by the policy program aborts the fill.
+///
+///
+/// Accounts passed, all read-only and unsigned:
+///
+/// 0. The escrow account holding the escrow info
+/// 1. The account of the person taking the trade
+/// 2. The initializer's main account
+/// 3. The PDA's temp token account holding the escrowed tokens
+/// 4. The initializer's token account that will receive tokens
+/// 5. Zero or more extra accounts the taker listed after the policy program, e.g. attestations or risk configs
+///
+/// Data: `POLICY_CHECK_TAG`, then the amount the taker expects to receive and the escrow's expected amount, both as little-endian u64
+pub fn check(
+    policy_program_id: &Pubkey,
+    escrow_pubkey: &Pubkey,
+    taker_pubkey: &Pubkey,
+    escrow_info: &Escrow,
+    amount: u64,
+    extra_account_pubkeys: &[Pubkey]
+) -> Instruction {
+    let mut data = Vec::with_capacity(17);
+    data.push(POLICY_CHECK_TAG);
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&escrow_info.expected_amount.to_le_bytes());
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*escrow_pubkey, false),
+        AccountMeta::new_readonly(*taker_pubkey, false),
+        AccountMeta::new_readonly(escrow_info.initializer_pubkey, false),
+        AccountMeta::new_readonly(escrow_info.temp_token_account_pubkey, false),
+        AccountMeta::new_readonly(escrow_info.initializer_token_to_receive_account_pubkey, false),
+    ];
+    accounts.extend(
+        extra_account_pubkeys
+            .iter()
+            .map(|extra_account_pubkey| AccountMeta::new_readonly(*extra_account_pubkey, false))
+    );
+
+    Instruction {
+        program_id: *policy_program_id,
+        accounts,
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_layout() {
+        let policy_program_id = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+        let taker_pubkey = Pubkey::new_unique();
+        let escrow_info = Escrow {
+            is_initialized: true,
+            initializer_pubkey: Pubkey::new_unique(),
+            temp_token_account_pubkey: Pubkey::new_unique(),
+            initializer_token_to_receive_account_pubkey: Pubkey::new_unique(),
+            expected_amount: 7,
+            group_pubkey: Pubkey::default(),
+            policy_program_pubkey: policy_program_id,
+            token_to_receive_owner_pubkey: Pubkey::new_unique(),
+            token_to_receive_mint_pubkey: Pubkey::new_unique(),
+        };
+
+        let extra_pubkeys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let ix = check(&policy_program_id, &escrow_pubkey, &taker_pubkey, &escrow_info, 3, &extra_pubkeys);
+
+        assert_eq!(ix.program_id, policy_program_id);
+        assert_eq!(ix.data.len(), 17);
+        assert_eq!(ix.data[0], POLICY_CHECK_TAG);
+        assert_eq!(ix.data[1..9], 3u64.to_le_bytes());
+        assert_eq!(ix.data[9..17], 7u64.to_le_bytes());
+
+        let expected_pubkeys = [
+            escrow_pubkey,
+            taker_pubkey,
+            escrow_info.initializer_pubkey,
+            escrow_info.temp_token_account_pubkey,
+            escrow_info.initializer_token_to_receive_account_pubkey,
+            extra_pubkeys[0],
+            extra_pubkeys[1],
+        ];
+        assert_eq!(ix.accounts.len(), expected_pubkeys.len());
+        for (meta, pubkey) in ix.accounts.iter().zip(expected_pubkeys.iter()) {
+            assert_eq!(meta.pubkey, *pubkey);
+            assert!(!meta.is_signer);
+            assert!(!meta.is_writable);
+        }
+    }
+}
@@ -7,12 +7,14 @@ use solana_program::{
     sysvar::{rent::Rent, Sysvar},
     program_pack::{Pack, IsInitialized},
     program::{invoke, invoke_signed},
+    system_program,
 };
 use spl_token::state::Account as TokenAccount;
 use crate::{
     error::EscrowError, 
     instruction::EscrowInstruction, 
-    state::Escrow
+    policy,
+    state::{Escrow, EscrowGroup, MAX_GROUP_MEMBERS}
 };
 
 pub struct Processor;
@@ -26,13 +28,25 @@ impl Processor {
         let instruction = EscrowInstruction::unpack(instruction_data)?;
         
         match instruction {
-            EscrowInstruction::InitEscrow { amount } => {
+            EscrowInstruction::InitEscrow { amount, with_policy } => {
                 msg!("Instruction: InitEscrow");
-                Self::process_init_escrow(accounts, amount, program_id)
+                Self::process_init_escrow(accounts, amount, with_policy, program_id)
             },
             EscrowInstruction::Exchange { amount } => {
                 msg!("Instruction: Exchange");
                 Self::process_exchange_escrow(accounts, amount, program_id)
+            },
+            EscrowInstruction::FlipAndRelist { amount, expected_amount, with_policy } => {
+                msg!("Instruction: FlipAndRelist");
+                Self::process_flip_and_relist(accounts, amount, expected_amount, with_policy, program_id)
+            },
+            EscrowInstruction::GroupEscrows => {
+                msg!("Instruction: GroupEscrows");
+                Self::process_group_escrows(accounts, program_id)
+            },
+            EscrowInstruction::ExchangeGroup { amounts } => {
+                msg!("Instruction: ExchangeGroup");
+                Self::process_exchange_group(accounts, &amounts, program_id)
             }
         }
     }
@@ -40,6 +54,7 @@ impl Processor {
     fn process_init_escrow(
         accounts: &[AccountInfo],
         amount: u64,
+        with_policy: bool,
         program_id: &Pubkey
     ) -> ProgramResult {
 
@@ -57,7 +72,22 @@ impl Processor {
             return Err(ProgramError::IncorrectProgramId);
         }
 
+        // recorded so fills can detect the receive account being closed or reassigned
+        let token_to_receive_account_info = TokenAccount::unpack(
+            &token_to_receive_account.try_borrow_data()?
+        )?;
+
         let escrow_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // an escrow without a policy program is stored with the default pubkey
+        let policy_program_pubkey = if with_policy {
+            let policy_program = next_account_info(account_info_iter)?;
+            Self::check_policy_program(policy_program, program_id)?;
+            *policy_program.key
+        } else {
+            Pubkey::default()
+        };
         
         let rent = Rent::get()?;
 
@@ -75,12 +105,15 @@ impl Processor {
         escrow_info.temp_token_account_pubkey = *temp_token_account.key;
         escrow_info.initializer_token_to_receive_account_pubkey = *token_to_receive_account.key;
         escrow_info.expected_amount = amount;
+        escrow_info.group_pubkey = Pubkey::default();
+        escrow_info.policy_program_pubkey = policy_program_pubkey;
+        escrow_info.token_to_receive_owner_pubkey = token_to_receive_account_info.owner;
+        escrow_info.token_to_receive_mint_pubkey = token_to_receive_account_info.mint;
 
         Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
 
         let (pda, _bump_seed) = Pubkey::find_program_address(&[b"escrow"], program_id);
 
-        let token_program = next_account_info(account_info_iter)?;
         let owner_change_ix 
             = spl_token::instruction::set_authority(
                 token_program.key, 
@@ -109,6 +142,16 @@ impl Processor {
         amount: u64,
         program_id: &Pubkey
     ) -> ProgramResult {
+        // grouped escrows can only be filled through ExchangeGroup
+        Self::settle_escrow(accounts, amount, &Pubkey::default(), program_id)
+    }
+
+    fn settle_escrow(
+        accounts: &[AccountInfo],
+        amount: u64,
+        group_pubkey: &Pubkey,
+        program_id: &Pubkey
+    ) -> ProgramResult {
 
         let account_info_iter = &mut accounts.iter();
 
@@ -121,6 +164,7 @@ impl Processor {
         let escrow_account = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
         let pda_account = next_account_info(account_info_iter)?;
+        let policy_accounts = account_info_iter.as_slice();
 
         // We check the taker is signing the Transaction
         if !taker.is_signer {
@@ -136,7 +180,7 @@ impl Processor {
             return Err(EscrowError::EscrowAmountMismatch.into())
         }
 
-        let escrow_account_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+        let escrow_account_info = Escrow::unpack_any(&escrow_account.try_borrow_data()?)?;
 
         // we verify that initializer is correct, that temp token is correct, and that token to receive is correct
         if escrow_account_info.initializer_pubkey != *initializer_main_account.key {
@@ -151,6 +195,56 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        if escrow_account_info.group_pubkey != *group_pubkey {
+            return Err(EscrowError::EscrowGroupMismatch.into());
+        }
+
+        // we verify the initializer's receive account wasn't closed or reassigned since the escrow was created
+        Self::check_receive_account(
+            initializer_token_to_receive_account,
+            &escrow_account_info.token_to_receive_owner_pubkey,
+            &escrow_account_info.token_to_receive_mint_pubkey
+        )?;
+
+        // the escrow's policy program gets to reject the fill before any tokens move
+        if escrow_account_info.policy_program_pubkey != Pubkey::default() {
+            let policy_program_index = policy_accounts
+                .iter()
+                .position(|policy_program| *policy_program.key == escrow_account_info.policy_program_pubkey)
+                .ok_or(EscrowError::PolicyProgramMismatch)?;
+            let (policy_program, extra_accounts) = policy_accounts[policy_program_index..]
+                .split_first()
+                .ok_or(EscrowError::PolicyProgramMismatch)?;
+
+            // every account after the policy program is forwarded for it to read
+            let extra_account_pubkeys: Vec<Pubkey> = extra_accounts
+                .iter()
+                .map(|extra_account| *extra_account.key)
+                .collect();
+
+            let policy_check_ix = policy::check(
+                policy_program.key,
+                escrow_account.key,
+                taker.key,
+                &escrow_account_info,
+                amount,
+                &extra_account_pubkeys
+            );
+
+            let mut policy_check_accounts = vec![
+                escrow_account.clone(),
+                taker.clone(),
+                initializer_main_account.clone(),
+                temp_token_account.clone(),
+                initializer_token_to_receive_account.clone(),
+            ];
+            policy_check_accounts.extend_from_slice(extra_accounts);
+            policy_check_accounts.push(policy_program.clone());
+
+            msg!("Calling the policy program to validate the exchange...");
+            invoke(&policy_check_ix, &policy_check_accounts)?;
+        }
+
         // transfer amount from taker to initializer
         let transfer_to_initializer_ix 
             = spl_token::instruction::transfer(
@@ -232,6 +326,538 @@ impl Processor {
 
         Ok(())
     }
+
+    fn process_flip_and_relist(
+        accounts: &[AccountInfo],
+        amount: u64,
+        expected_amount: u64,
+        with_policy: bool,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let initializer = next_account_info(account_info_iter)?;
+        let received_token_account = next_account_info(account_info_iter)?;
+        let temp_token_account = next_account_info(account_info_iter)?;
+        let token_to_receive_account = next_account_info(account_info_iter)?;
+        let escrow_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let policy_programs = account_info_iter.as_slice();
+
+        if !initializer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // a self-transfer would hand the whole received account to the PDA instead of `amount`
+        if received_token_account.key == temp_token_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // a zero transfer would list whatever the temp account already held
+        if amount == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // the relist has to go in the opposite direction of the received tokens
+        let received_token_account_info = TokenAccount::unpack(
+            &received_token_account.try_borrow_data()?
+        )?;
+        let token_to_receive_account_info = TokenAccount::unpack(
+            &token_to_receive_account.try_borrow_data()?
+        )?;
+
+        if received_token_account_info.mint == token_to_receive_account_info.mint {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // move the received tokens into the new temp account
+        let transfer_to_temp_ix
+            = spl_token::instruction::transfer(
+                token_program.key,
+                received_token_account.key,
+                temp_token_account.key,
+                initializer.key,
+                &[initializer.key],
+                amount,
+            )?;
+
+        msg!("Calling the token program to transfer received tokens to the new temp account...");
+        invoke(
+            &transfer_to_temp_ix,
+            &[
+                received_token_account.clone(),
+                temp_token_account.clone(),
+                initializer.clone(),
+                token_program.clone()
+            ]
+        )?;
+
+        // the rest is a regular escrow initialization on the temp account
+        let mut init_accounts = vec![
+            initializer.clone(),
+            temp_token_account.clone(),
+            token_to_receive_account.clone(),
+            escrow_account.clone(),
+            token_program.clone(),
+        ];
+        init_accounts.extend_from_slice(policy_programs);
+
+        Self::process_init_escrow(&init_accounts, expected_amount, with_policy, program_id)
+    }
+
+    fn process_group_escrows(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey
+    ) -> ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let initializer = next_account_info(account_info_iter)?;
+        if !initializer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let group_account = next_account_info(account_info_iter)?;
+        if group_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut group_info = EscrowGroup::unpack_unchecked(&group_account.try_borrow_data()?)?;
+        if group_info.is_initialized() {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+
+        let escrow_accounts = account_info_iter.as_slice();
+        if escrow_accounts.len() < 2 || escrow_accounts.len() > MAX_GROUP_MEMBERS {
+            return Err(EscrowError::InvalidGroupSize.into());
+        }
+
+        for (member_pubkey, escrow_account) in group_info.member_pubkeys.iter_mut().zip(escrow_accounts) {
+            if escrow_account.owner != program_id {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+
+            // packing each escrow right away also rejects the same escrow being listed twice,
+            // legacy sized escrows can't hold a group and fail to unpack here
+            let mut escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+            if escrow_info.initializer_pubkey != *initializer.key {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            if escrow_info.group_pubkey != Pubkey::default() {
+                return Err(EscrowError::EscrowGroupMismatch.into());
+            }
+
+            escrow_info.group_pubkey = *group_account.key;
+            Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
+
+            *member_pubkey = *escrow_account.key;
+        }
+
+        let rent = Rent::get()?;
+
+        if !rent.is_exempt(group_account.lamports(), group_account.data_len()) {
+            return Err(EscrowError::NotRentExempt.into());
+        }
+
+        group_info.is_initialized = true;
+        group_info.initializer_pubkey = *initializer.key;
+        group_info.member_count = escrow_accounts.len() as u8;
+
+        EscrowGroup::pack(group_info, &mut group_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn process_exchange_group(
+        accounts: &[AccountInfo],
+        amounts: &[u64],
+        program_id: &Pubkey
+    ) -> ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let taker = next_account_info(account_info_iter)?;
+        let group_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+
+        if group_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let group_info = EscrowGroup::unpack(&group_account.try_borrow_data()?)?;
+
+        // every escrow of the group has to be filled, the transaction failing otherwise
+        let members = group_info.members();
+        let remaining_accounts = account_info_iter.as_slice();
+        if amounts.len() != members.len() || remaining_accounts.len() < members.len() * 6 {
+            return Err(EscrowError::InvalidGroupSize.into());
+        }
+
+        let (member_accounts, policy_accounts) = remaining_accounts.split_at(members.len() * 6);
+
+        for ((member_pubkey, amount), escrow_accounts) in members.iter().zip(amounts).zip(member_accounts.chunks_exact(6)) {
+            let escrow_account = &escrow_accounts[5];
+            if escrow_account.key != member_pubkey {
+                return Err(EscrowError::EscrowGroupMismatch.into());
+            }
+
+            let mut settle_accounts = vec![
+                taker.clone(),
+                escrow_accounts[0].clone(),
+                escrow_accounts[1].clone(),
+                escrow_accounts[2].clone(),
+                escrow_accounts[3].clone(),
+                escrow_accounts[4].clone(),
+                escrow_account.clone(),
+                token_program.clone(),
+                pda_account.clone(),
+            ];
+            settle_accounts.extend_from_slice(policy_accounts);
+
+            msg!("Settling grouped escrow {}...", member_pubkey);
+            Self::settle_escrow(&settle_accounts, *amount, group_account.key, program_id)?;
+        }
+
+        // all escrows share the initializer, so the group rent goes back to the first one's main account
+        let initializer_main_account = &member_accounts[3];
+        if *initializer_main_account.key != group_info.initializer_pubkey {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        msg!("Closing the group account...");
+        **initializer_main_account.lamports.borrow_mut() = initializer_main_account.lamports()
+            .checked_add(group_account.lamports())
+            .ok_or(EscrowError::AmountOverflow)?;
+        **group_account.lamports.borrow_mut() = 0;
+        *group_account.try_borrow_mut_data()? = &mut [];
+
+        Ok(())
+    }
+
+    fn check_policy_program(
+        policy_program: &AccountInfo,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+        if !policy_program.executable {
+            return Err(EscrowError::PolicyProgramMismatch.into());
+        }
+
+        // these would never approve a policy check, locking the escrow for good
+        if policy_program.key == program_id
+            || *policy_program.key == spl_token::id()
+            || *policy_program.key == system_program::id()
+        {
+            return Err(EscrowError::PolicyProgramMismatch.into());
+        }
+
+        Ok(())
+    }
+
+    fn check_receive_account(
+        receive_account: &AccountInfo,
+        expected_owner: &Pubkey,
+        expected_mint: &Pubkey
+    ) -> ProgramResult {
+        if *receive_account.owner != spl_token::id() {
+            return Err(EscrowError::StaleReceiveAccount.into());
+        }
+
+        let receive_account_info = TokenAccount::unpack(&receive_account.try_borrow_data()?)
+            .map_err(|_| EscrowError::StaleReceiveAccount)?;
+
+        // legacy escrows have no recorded owner or mint to compare against
+        if *expected_owner == Pubkey::default() {
+            return Ok(());
+        }
+
+        if receive_account_info.owner != *expected_owner || receive_account_info.mint != *expected_mint {
+            return Err(EscrowError::StaleReceiveAccount.into());
+        }
+
+        Ok(())
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_token::state::AccountState;
+
+    fn token_account_data(mint: Pubkey, owner: Pubkey) -> Vec<u8> {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint,
+                owner,
+                state: AccountState::Initialized,
+                ..TokenAccount::default()
+            },
+            &mut data
+        ).unwrap();
+        data
+    }
+
+    fn escrow_data(initializer_pubkey: Pubkey, group_pubkey: Pubkey) -> Vec<u8> {
+        let mut data = vec![0; Escrow::LEN];
+        Escrow::pack(
+            Escrow {
+                is_initialized: true,
+                initializer_pubkey,
+                temp_token_account_pubkey: Pubkey::default(),
+                initializer_token_to_receive_account_pubkey: Pubkey::default(),
+                expected_amount: 0,
+                group_pubkey,
+                policy_program_pubkey: Pubkey::default(),
+                token_to_receive_owner_pubkey: Pubkey::default(),
+                token_to_receive_mint_pubkey: Pubkey::default(),
+            },
+            &mut data
+        ).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_flip_and_relist_rejects_zero_amount_and_same_mint() {
+        let program_id = Pubkey::new_unique();
+        let token_program_id = spl_token::id();
+        let initializer_key = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+
+        let mut lamports = [0u64; 6];
+        let mut initializer_data = [];
+        let mut received_data = token_account_data(mint, initializer_key);
+        let mut temp_data = token_account_data(mint, initializer_key);
+        let mut receive_data = token_account_data(mint, initializer_key);
+        let mut escrow_data = vec![0; Escrow::LEN];
+        let mut token_program_data = [];
+        let [l0, l1, l2, l3, l4, l5] = &mut lamports;
+
+        let accounts = [
+            AccountInfo::new(&initializer_key, true, false, l0, &mut initializer_data, &program_id, false, 0),
+            AccountInfo::new(&keys[0], false, true, l1, &mut received_data, &token_program_id, false, 0),
+            AccountInfo::new(&keys[1], false, true, l2, &mut temp_data, &token_program_id, false, 0),
+            AccountInfo::new(&keys[2], false, false, l3, &mut receive_data, &token_program_id, false, 0),
+            AccountInfo::new(&keys[3], false, true, l4, &mut escrow_data, &program_id, false, 0),
+            AccountInfo::new(&token_program_id, false, false, l5, &mut token_program_data, &program_id, true, 0),
+        ];
+
+        assert_eq!(
+            Processor::process_flip_and_relist(&accounts, 0, 10, false, &program_id),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            Processor::process_flip_and_relist(&accounts, 5, 10, false, &program_id),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_check_receive_account() {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_program_id = spl_token::id();
+        let system_program_id = system_program::id();
+        let stale = Err(EscrowError::StaleReceiveAccount.into());
+
+        let mut lamports = 0;
+        let mut data = token_account_data(mint, owner);
+        let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &token_program_id, false, 0);
+        assert_eq!(Processor::check_receive_account(&account, &owner, &mint), Ok(()));
+        assert_eq!(Processor::check_receive_account(&account, &Pubkey::new_unique(), &mint), stale);
+        assert_eq!(Processor::check_receive_account(&account, &owner, &Pubkey::new_unique()), stale);
+
+        // legacy escrows have no recorded owner or mint
+        assert_eq!(Processor::check_receive_account(&account, &Pubkey::default(), &Pubkey::default()), Ok(()));
+
+        let mut lamports = 0;
+        let mut data = [];
+        let closed_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &system_program_id, false, 0);
+        assert_eq!(Processor::check_receive_account(&closed_account, &owner, &mint), stale);
+
+        let mut lamports = 0;
+        let mut data = vec![0; TokenAccount::LEN];
+        let uninitialized_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &token_program_id, false, 0);
+        assert_eq!(Processor::check_receive_account(&uninitialized_account, &owner, &mint), stale);
+    }
+
+    #[test]
+    fn test_group_escrows_checks() {
+        let program_id = Pubkey::new_unique();
+        let initializer_key = Pubkey::new_unique();
+        let group_key = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+
+        let mut lamports = [0u64; 6];
+        let mut initializer_data = [];
+        let mut group_data = vec![0; EscrowGroup::LEN];
+        let mut escrow_0_data = escrow_data(initializer_key, Pubkey::default());
+        let mut escrow_1_data = escrow_data(initializer_key, Pubkey::default());
+        let mut other_initializer_data = escrow_data(Pubkey::new_unique(), Pubkey::default());
+        let mut grouped_data = escrow_data(initializer_key, Pubkey::new_unique());
+        let [l0, l1, l2, l3, l4, l5] = &mut lamports;
+
+        let initializer = AccountInfo::new(&initializer_key, true, false, l0, &mut initializer_data, &program_id, false, 0);
+        let group = AccountInfo::new(&group_key, false, true, l1, &mut group_data, &program_id, false, 0);
+        let escrow_0 = AccountInfo::new(&keys[0], false, true, l2, &mut escrow_0_data, &program_id, false, 0);
+        let escrow_1 = AccountInfo::new(&keys[1], false, true, l3, &mut escrow_1_data, &program_id, false, 0);
+        let other_initializer = AccountInfo::new(&keys[2], false, true, l4, &mut other_initializer_data, &program_id, false, 0);
+        let grouped = AccountInfo::new(&keys[3], false, true, l5, &mut grouped_data, &program_id, false, 0);
+
+        let mut accounts = vec![initializer.clone(), group.clone(), escrow_0.clone()];
+        assert_eq!(
+            Processor::process_group_escrows(&accounts, &program_id),
+            Err(EscrowError::InvalidGroupSize.into())
+        );
+
+        accounts.extend((0..MAX_GROUP_MEMBERS).map(|_| escrow_1.clone()));
+        assert_eq!(
+            Processor::process_group_escrows(&accounts, &program_id),
+            Err(EscrowError::InvalidGroupSize.into())
+        );
+
+        let accounts = [initializer.clone(), group.clone(), escrow_0.clone(), other_initializer];
+        assert_eq!(
+            Processor::process_group_escrows(&accounts, &program_id),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let accounts = [initializer.clone(), group.clone(), escrow_1.clone(), grouped];
+        assert_eq!(
+            Processor::process_group_escrows(&accounts, &program_id),
+            Err(EscrowError::EscrowGroupMismatch.into())
+        );
+
+        // escrow_0 and escrow_1 were tagged by the failed attempts above, which a real transaction would roll back
+        let escrow_2_key = Pubkey::new_unique();
+        let mut escrow_2_lamports = 0;
+        let mut escrow_2_data = escrow_data(initializer_key, Pubkey::default());
+        let escrow_2 = AccountInfo::new(&escrow_2_key, false, true, &mut escrow_2_lamports, &mut escrow_2_data, &program_id, false, 0);
+        let accounts = [initializer, group, escrow_2.clone(), escrow_2];
+        assert_eq!(
+            Processor::process_group_escrows(&accounts, &program_id),
+            Err(EscrowError::EscrowGroupMismatch.into())
+        );
+    }
+
+    #[test]
+    fn test_exchange_group_checks() {
+        let program_id = Pubkey::new_unique();
+        let token_program_id = spl_token::id();
+        let group_key = Pubkey::new_unique();
+        let member_keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let other_key = Pubkey::new_unique();
+
+        let mut member_pubkeys = [Pubkey::default(); MAX_GROUP_MEMBERS];
+        member_pubkeys[..2].copy_from_slice(&member_keys);
+        let mut group_data = vec![0; EscrowGroup::LEN];
+        EscrowGroup::pack(
+            EscrowGroup {
+                is_initialized: true,
+                initializer_pubkey: Pubkey::new_unique(),
+                member_count: 2,
+                member_pubkeys,
+            },
+            &mut group_data
+        ).unwrap();
+
+        let mut lamports = [0u64; 5];
+        let mut other_data = [];
+        let mut token_program_data = [];
+        let mut escrow_0_data = [];
+        let mut escrow_1_data = [];
+        let [l0, l1, l2, l3, l4] = &mut lamports;
+
+        let other = AccountInfo::new(&other_key, true, true, l0, &mut other_data, &program_id, false, 0);
+        let group = AccountInfo::new(&group_key, false, true, l1, &mut group_data, &program_id, false, 0);
+        let token_program = AccountInfo::new(&token_program_id, false, false, l2, &mut token_program_data, &program_id, true, 0);
+        let escrow_0 = AccountInfo::new(&member_keys[0], false, true, l3, &mut escrow_0_data, &program_id, false, 0);
+        let escrow_1 = AccountInfo::new(&member_keys[1], false, true, l4, &mut escrow_1_data, &program_id, false, 0);
+
+        let mut accounts = vec![other.clone(), group, token_program, other.clone()];
+        for escrow in [&escrow_1, &escrow_0] {
+            accounts.extend((0..5).map(|_| other.clone()));
+            accounts.push(escrow.clone());
+        }
+
+        assert_eq!(
+            Processor::process_exchange_group(&accounts, &[0], &program_id),
+            Err(EscrowError::InvalidGroupSize.into())
+        );
+        assert_eq!(
+            Processor::process_exchange_group(&accounts[..10], &[0, 0], &program_id),
+            Err(EscrowError::InvalidGroupSize.into())
+        );
+        assert_eq!(
+            Processor::process_exchange_group(&accounts, &[0, 0], &program_id),
+            Err(EscrowError::EscrowGroupMismatch.into())
+        );
+    }
 
+    #[test]
+    fn test_exchange_rejects_grouped_escrow() {
+        let program_id = Pubkey::new_unique();
+        let token_program_id = spl_token::id();
+        let initializer_key = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+
+        let mut escrow = Escrow::unpack(&escrow_data(initializer_key, Pubkey::new_unique())).unwrap();
+        escrow.temp_token_account_pubkey = keys[2];
+        escrow.initializer_token_to_receive_account_pubkey = keys[3];
+        let mut escrow_data = vec![0; Escrow::LEN];
+        Escrow::pack(escrow, &mut escrow_data).unwrap();
+
+        let mut lamports = [0u64; 9];
+        let mut taker_data = [];
+        let mut sending_data = [];
+        let mut receiving_data = [];
+        let mut temp_data = token_account_data(Pubkey::new_unique(), Pubkey::new_unique());
+        let mut initializer_data = [];
+        let mut receive_data = [];
+        let mut token_program_data = [];
+        let mut pda_data = [];
+        let [l0, l1, l2, l3, l4, l5, l6, l7, l8] = &mut lamports;
+
+        let accounts = [
+            AccountInfo::new(&keys[0], true, false, l0, &mut taker_data, &program_id, false, 0),
+            AccountInfo::new(&keys[1], false, true, l1, &mut sending_data, &token_program_id, false, 0),
+            AccountInfo::new(&keys[4], false, true, l2, &mut receiving_data, &token_program_id, false, 0),
+            AccountInfo::new(&keys[2], false, true, l3, &mut temp_data, &token_program_id, false, 0),
+            AccountInfo::new(&initializer_key, false, true, l4, &mut initializer_data, &program_id, false, 0),
+            AccountInfo::new(&keys[3], false, true, l5, &mut receive_data, &token_program_id, false, 0),
+            AccountInfo::new(&keys[5], false, true, l6, &mut escrow_data, &program_id, false, 0),
+            AccountInfo::new(&token_program_id, false, false, l7, &mut token_program_data, &program_id, true, 0),
+            AccountInfo::new(&program_id, false, false, l8, &mut pda_data, &program_id, false, 0),
+        ];
+
+        assert_eq!(
+            Processor::process_exchange_escrow(&accounts, 0, &program_id),
+            Err(EscrowError::EscrowGroupMismatch.into())
+        );
+    }
+
+    #[test]
+    fn test_check_policy_program() {
+        let program_id = Pubkey::new_unique();
+        let loader_id = Pubkey::new_unique();
+        let policy_key = Pubkey::new_unique();
+        let mismatch = Err(EscrowError::PolicyProgramMismatch.into());
+
+        for (key, executable, expected) in [
+            (policy_key, true, Ok(())),
+            (policy_key, false, mismatch.clone()),
+            (program_id, true, mismatch.clone()),
+            (spl_token::id(), true, mismatch.clone()),
+            (system_program::id(), true, mismatch.clone()),
+        ] {
+            let mut lamports = 0;
+            let mut data = [];
+            let policy_program = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &loader_id, executable, 0);
+            assert_eq!(Processor::check_policy_program(&policy_program, &program_id), expected);
+        }
+    }
+}
@@ -12,6 +12,28 @@ pub struct Escrow {
     pub temp_token_account_pubkey: Pubkey,
     pub initializer_token_to_receive_account_pubkey: Pubkey,
     pub expected_amount: u64,
+    pub group_pubkey: Pubkey,
+    pub policy_program_pubkey: Pubkey,
+    pub token_to_receive_owner_pubkey: Pubkey,
+    pub token_to_receive_mint_pubkey: Pubkey,
+}
+
+/// Size of escrow accounts created before groups, policies and receive account recording were added
+pub const LEGACY_ESCROW_LEN: usize = 105;
+
+impl Escrow {
+    /// Unpacks an escrow in either the current or the legacy layout. Newer fields are appended
+    /// to the legacy layout, so a legacy escrow decodes as ungrouped, without a policy program
+    /// and without a recorded receive account owner or mint.
+    pub fn unpack_any(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != LEGACY_ESCROW_LEN {
+            return Escrow::unpack(src);
+        }
+
+        let mut padded = [0u8; Escrow::LEN];
+        padded[..LEGACY_ESCROW_LEN].copy_from_slice(src);
+        Escrow::unpack(&padded)
+    }
 }
 
 impl Sealed for Escrow {}
@@ -23,7 +45,7 @@ impl IsInitialized for Escrow {
 }
 
 impl Pack for Escrow {
-    const LEN: usize = 105;
+    const LEN: usize = 233;
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
         let src = array_ref![src, 0, Escrow::LEN];
@@ -33,8 +55,12 @@ impl Pack for Escrow {
             initializer_pubkey,
             temp_token_account_pubkey,
             initializer_token_to_receive_account_pubkey,
-            expected_amount
-        ) = array_refs![src, 1, 32, 32, 32, 8];
+            expected_amount,
+            group_pubkey,
+            policy_program_pubkey,
+            token_to_receive_owner_pubkey,
+            token_to_receive_mint_pubkey
+        ) = array_refs![src, 1, 32, 32, 32, 8, 32, 32, 32, 32];
 
         let is_initialized = match is_initialized {
             [0] => false,
@@ -48,6 +74,10 @@ impl Pack for Escrow {
             temp_token_account_pubkey: Pubkey::new_from_array(*temp_token_account_pubkey),
             initializer_token_to_receive_account_pubkey: Pubkey::new_from_array(*initializer_token_to_receive_account_pubkey),
             expected_amount: u64::from_le_bytes(*expected_amount),
+            group_pubkey: Pubkey::new_from_array(*group_pubkey),
+            policy_program_pubkey: Pubkey::new_from_array(*policy_program_pubkey),
+            token_to_receive_owner_pubkey: Pubkey::new_from_array(*token_to_receive_owner_pubkey),
+            token_to_receive_mint_pubkey: Pubkey::new_from_array(*token_to_receive_mint_pubkey),
         })
     }
 
@@ -60,7 +90,11 @@ impl Pack for Escrow {
             temp_token_account_pubkey_dst,
             initializer_token_to_receive_account_pubkey_dst,
             expected_amount_dst,
-        ) = mut_array_refs![dst, 1, 32, 32, 32, 8];
+            group_pubkey_dst,
+            policy_program_pubkey_dst,
+            token_to_receive_owner_pubkey_dst,
+            token_to_receive_mint_pubkey_dst,
+        ) = mut_array_refs![dst, 1, 32, 32, 32, 8, 32, 32, 32, 32];
 
         let Escrow {
             is_initialized,
@@ -68,6 +102,10 @@ impl Pack for Escrow {
             temp_token_account_pubkey,
             initializer_token_to_receive_account_pubkey,
             expected_amount,
+            group_pubkey,
+            policy_program_pubkey,
+            token_to_receive_owner_pubkey,
+            token_to_receive_mint_pubkey,
         } = self;
 
         is_initialized_dst[0] = *is_initialized as u8;
@@ -75,5 +113,161 @@ impl Pack for Escrow {
         temp_token_account_pubkey_dst.copy_from_slice(temp_token_account_pubkey.as_ref());
         initializer_token_to_receive_account_pubkey_dst.copy_from_slice(initializer_token_to_receive_account_pubkey.as_ref());
         *expected_amount_dst = expected_amount.to_le_bytes();
+        group_pubkey_dst.copy_from_slice(group_pubkey.as_ref());
+        policy_program_pubkey_dst.copy_from_slice(policy_program_pubkey.as_ref());
+        token_to_receive_owner_pubkey_dst.copy_from_slice(token_to_receive_owner_pubkey.as_ref());
+        token_to_receive_mint_pubkey_dst.copy_from_slice(token_to_receive_mint_pubkey.as_ref());
+    }
+}
+
+/// Maximum number of escrows that can be settled together in one group
+pub const MAX_GROUP_MEMBERS: usize = 8;
+
+pub struct EscrowGroup {
+    pub is_initialized: bool,
+    pub initializer_pubkey: Pubkey,
+    pub member_count: u8,
+    pub member_pubkeys: [Pubkey; MAX_GROUP_MEMBERS],
+}
+
+impl EscrowGroup {
+    pub fn members(&self) -> &[Pubkey] {
+        &self.member_pubkeys[..self.member_count as usize]
+    }
+}
+
+impl Sealed for EscrowGroup {}
+
+impl IsInitialized for EscrowGroup {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for EscrowGroup {
+    const LEN: usize = 1 + 32 + 1 + 32 * MAX_GROUP_MEMBERS;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, EscrowGroup::LEN];
+
+        let (
+            is_initialized,
+            initializer_pubkey,
+            member_count,
+            member_pubkeys_src
+        ) = array_refs![src, 1, 32, 1, 32 * MAX_GROUP_MEMBERS];
+
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _=> return Err(ProgramError::InvalidAccountData)
+        };
+
+        let member_count = member_count[0];
+        if member_count as usize > MAX_GROUP_MEMBERS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut member_pubkeys = [Pubkey::default(); MAX_GROUP_MEMBERS];
+        for (member_pubkey, src) in member_pubkeys.iter_mut().zip(member_pubkeys_src.chunks_exact(32)) {
+            *member_pubkey = Pubkey::try_from(src).map_err(|_| ProgramError::InvalidAccountData)?;
+        }
+
+        Ok(EscrowGroup {
+            is_initialized,
+            initializer_pubkey: Pubkey::new_from_array(*initializer_pubkey),
+            member_count,
+            member_pubkeys,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, EscrowGroup::LEN];
+
+        let (
+            is_initialized_dst,
+            initializer_pubkey_dst,
+            member_count_dst,
+            member_pubkeys_dst,
+        ) = mut_array_refs![dst, 1, 32, 1, 32 * MAX_GROUP_MEMBERS];
+
+        let EscrowGroup {
+            is_initialized,
+            initializer_pubkey,
+            member_count,
+            member_pubkeys,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        initializer_pubkey_dst.copy_from_slice(initializer_pubkey.as_ref());
+        member_count_dst[0] = *member_count;
+        for (dst, member_pubkey) in member_pubkeys_dst.chunks_exact_mut(32).zip(member_pubkeys.iter()) {
+            dst.copy_from_slice(member_pubkey.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escrow_group_pack_unpack() {
+        let mut member_pubkeys = [Pubkey::default(); MAX_GROUP_MEMBERS];
+        member_pubkeys[0] = Pubkey::new_unique();
+        member_pubkeys[1] = Pubkey::new_unique();
+        member_pubkeys[2] = Pubkey::new_unique();
+
+        let initializer_pubkey = Pubkey::new_unique();
+        let group = EscrowGroup {
+            is_initialized: true,
+            initializer_pubkey,
+            member_count: 3,
+            member_pubkeys,
+        };
+
+        let mut dst = [0u8; EscrowGroup::LEN];
+        EscrowGroup::pack(group, &mut dst).unwrap();
+        let unpacked = EscrowGroup::unpack(&dst).unwrap();
+
+        assert!(unpacked.is_initialized);
+        assert_eq!(unpacked.initializer_pubkey, initializer_pubkey);
+        assert_eq!(unpacked.member_count, 3);
+        assert_eq!(unpacked.members(), &member_pubkeys[..3]);
+        assert_eq!(unpacked.member_pubkeys, member_pubkeys);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_escrow_group_unpack_too_many_members() {
+        let mut src = [0u8; EscrowGroup::LEN];
+        src[0] = 1;
+        src[33] = MAX_GROUP_MEMBERS as u8 + 1;
+
+        assert_eq!(
+            EscrowGroup::unpack(&src).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_escrow_unpack_any_legacy() {
+        let initializer_pubkey = Pubkey::new_unique();
+        let mut src = [0u8; LEGACY_ESCROW_LEN];
+        src[0] = 1;
+        src[1..33].copy_from_slice(initializer_pubkey.as_ref());
+        src[97..105].copy_from_slice(&42u64.to_le_bytes());
+
+        assert_eq!(
+            Escrow::unpack(&src).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+
+        let escrow = Escrow::unpack_any(&src).unwrap();
+        assert!(escrow.is_initialized);
+        assert_eq!(escrow.initializer_pubkey, initializer_pubkey);
+        assert_eq!(escrow.expected_amount, 42);
+        assert_eq!(escrow.group_pubkey, Pubkey::default());
+        assert_eq!(escrow.policy_program_pubkey, Pubkey::default());
+        assert_eq!(escrow.token_to_receive_owner_pubkey, Pubkey::default());
+    }
+}